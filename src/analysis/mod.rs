@@ -0,0 +1,236 @@
+use super::engine;
+use std::collections::HashSet;
+
+pub struct DialogReport {
+    unreachable: Vec<engine::DialogNodeId>,
+    dead_ends: Vec<engine::DialogNodeId>,
+    soft_lock_cycles: Vec<Vec<engine::DialogNodeId>>,
+}
+
+impl DialogReport {
+    pub fn unreachable(&self) -> &Vec<engine::DialogNodeId> {
+        &self.unreachable
+    }
+    pub fn dead_ends(&self) -> &Vec<engine::DialogNodeId> {
+        &self.dead_ends
+    }
+    pub fn soft_lock_cycles(&self) -> &Vec<Vec<engine::DialogNodeId>> {
+        &self.soft_lock_cycles
+    }
+}
+
+impl engine::Dialog {
+    pub fn analyze(&self) -> DialogReport {
+        let mut reachable = HashSet::new();
+        collect_reachable(self, self.start_node().id().clone(), &mut reachable);
+
+        let unreachable = self
+            .all_nodes()
+            .map(|node| node.id().clone())
+            .filter(|id| !reachable.contains(id))
+            .collect();
+
+        // The dialog model has no "intended ending" marker, so every reachable
+        // node with no links out is reported, including legitimate endings.
+        let dead_ends = reachable
+            .iter()
+            .filter(|id| self.get_node(id).links().is_empty())
+            .cloned()
+            .collect();
+
+        let mut soft_lock_cycles = Vec::new();
+        let mut visited = HashSet::new();
+        let mut roots: Vec<_> = reachable
+            .iter()
+            .filter(|id| *id != self.start_node().id())
+            .cloned()
+            .collect();
+        roots.sort_by_key(|id| id.to_string());
+        roots.insert(0, self.start_node().id().clone());
+        for node_id in roots {
+            find_once_only_cycles(
+                self,
+                node_id,
+                &mut Vec::new(),
+                &mut HashSet::new(),
+                &mut visited,
+                &mut soft_lock_cycles,
+            );
+        }
+
+        DialogReport {
+            unreachable,
+            dead_ends,
+            soft_lock_cycles,
+        }
+    }
+}
+
+fn collect_reachable(
+    dialog: &engine::Dialog,
+    current: engine::DialogNodeId,
+    visited: &mut HashSet<engine::DialogNodeId>,
+) {
+    if !visited.insert(current.clone()) {
+        return;
+    }
+    for link in dialog.get_node(&current).links() {
+        collect_reachable(dialog, link.to().clone(), visited);
+    }
+}
+
+fn find_once_only_cycles(
+    dialog: &engine::Dialog,
+    current: engine::DialogNodeId,
+    path: &mut Vec<engine::DialogNodeId>,
+    on_path: &mut HashSet<engine::DialogNodeId>,
+    visited: &mut HashSet<engine::DialogNodeId>,
+    cycles: &mut Vec<Vec<engine::DialogNodeId>>,
+) {
+    if !visited.insert(current.clone()) {
+        return;
+    }
+    path.push(current.clone());
+    on_path.insert(current.clone());
+    for link in dialog.get_node(&current).links() {
+        if *link.condition() != engine::DialogLinkCondition::OnlyIfNotYetChosen {
+            continue;
+        }
+        let target = link.to();
+        if on_path.contains(target) {
+            let start = path.iter().position(|id| id == target).unwrap();
+            let cycle = &path[start..];
+            if !cycle.iter().any(|id| has_non_once_only_link(dialog, id)) {
+                cycles.push(cycle.to_vec());
+            }
+        } else {
+            find_once_only_cycles(dialog, target.clone(), path, on_path, visited, cycles);
+        }
+    }
+    path.pop();
+    on_path.remove(&current);
+}
+
+/// Whether `node` has a link that isn't gated on "not yet chosen"; a cycle
+/// through such a node is not reported. Where that link leads, and whether
+/// an expression condition on it holds, is not checked.
+fn has_non_once_only_link(dialog: &engine::Dialog, node: &engine::DialogNodeId) -> bool {
+    dialog
+        .get_node(node)
+        .links()
+        .iter()
+        .any(|link| *link.condition() != engine::DialogLinkCondition::OnlyIfNotYetChosen)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use engine::{DialogBuilder, DialogLink, DialogLinkCondition, DialogNode};
+
+    #[test]
+    fn finds_unreachable_and_dead_end_nodes() {
+        let dialog = DialogBuilder::new(DialogNode::new("Start", "Hello, World!"))
+            .add_node(DialogNode::new("End", "Goodbye, World!"))
+            .add_node(DialogNode::new("Orphan", "Nobody ever reads this."))
+            .add_link(DialogLink::new(
+                "Start",
+                "End",
+                "Bye!",
+                DialogLinkCondition::None,
+            ))
+            .build()
+            .unwrap();
+        let report = dialog.analyze();
+        assert_eq!(report.unreachable(), &vec!["Orphan".into()]);
+        let mut dead_ends = report.dead_ends().clone();
+        dead_ends.sort_by_key(|id| id.to_string());
+        assert_eq!(dead_ends, vec!["End".into()]);
+        assert_eq!(report.soft_lock_cycles().len(), 0);
+    }
+
+    #[test]
+    fn finds_soft_lock_cycle_through_once_only_links() {
+        let dialog = DialogBuilder::new(DialogNode::new("Start", "Hello, World!"))
+            .add_node(DialogNode::new("Branch", "Nice to meet you!"))
+            .add_link(DialogLink::new(
+                "Start",
+                "Branch",
+                "Hi!",
+                DialogLinkCondition::OnlyIfNotYetChosen,
+            ))
+            .add_link(DialogLink::new(
+                "Branch",
+                "Start",
+                "Go back",
+                DialogLinkCondition::OnlyIfNotYetChosen,
+            ))
+            .build()
+            .unwrap();
+        let report = dialog.analyze();
+        assert_eq!(
+            report.soft_lock_cycles(),
+            &vec![vec!["Start".into(), "Branch".into()]]
+        );
+    }
+
+    #[test]
+    fn finds_soft_lock_cycle_reached_through_an_unconditional_link() {
+        let dialog = DialogBuilder::new(DialogNode::new("Start", "Hello, World!"))
+            .add_node(DialogNode::new("A", "You wander in."))
+            .add_node(DialogNode::new("B", "A dead bolt clicks shut."))
+            .add_link(DialogLink::new(
+                "Start",
+                "A",
+                "Enter",
+                DialogLinkCondition::None,
+            ))
+            .add_link(DialogLink::new(
+                "A",
+                "B",
+                "Go deeper",
+                DialogLinkCondition::OnlyIfNotYetChosen,
+            ))
+            .add_link(DialogLink::new(
+                "B",
+                "A",
+                "Go back",
+                DialogLinkCondition::OnlyIfNotYetChosen,
+            ))
+            .build()
+            .unwrap();
+        let report = dialog.analyze();
+        assert_eq!(
+            report.soft_lock_cycles(),
+            &vec![vec!["A".into(), "B".into()]]
+        );
+    }
+
+    #[test]
+    fn once_only_cycle_with_an_escape_link_is_not_a_soft_lock() {
+        let dialog = DialogBuilder::new(DialogNode::new("Start", "Hello, World!"))
+            .add_node(DialogNode::new("Branch", "Nice to meet you!"))
+            .add_node(DialogNode::new("End", "Goodbye, World!"))
+            .add_link(DialogLink::new(
+                "Start",
+                "Branch",
+                "Hi!",
+                DialogLinkCondition::OnlyIfNotYetChosen,
+            ))
+            .add_link(DialogLink::new(
+                "Branch",
+                "Start",
+                "Go back",
+                DialogLinkCondition::OnlyIfNotYetChosen,
+            ))
+            .add_link(DialogLink::new(
+                "Branch",
+                "End",
+                "Leave",
+                DialogLinkCondition::None,
+            ))
+            .build()
+            .unwrap();
+        let report = dialog.analyze();
+        assert_eq!(report.soft_lock_cycles(), &Vec::<Vec<_>>::new());
+    }
+}
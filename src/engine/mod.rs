@@ -17,6 +17,24 @@ impl DialogBuilder {
     }
 
     pub fn build(self) -> Result<Dialog, DialogError> {
+        let mut missing_source = None;
+        let mut missing_target = None;
+        for node in self.nodes.values() {
+            for link in node.links() {
+                if missing_source.is_none() && !self.nodes.contains_key(link.from()) {
+                    missing_source = Some(link.from().clone());
+                }
+                if missing_target.is_none() && !self.nodes.contains_key(link.to()) {
+                    missing_target = Some(link.to().clone());
+                }
+            }
+        }
+        if missing_source.is_some() || missing_target.is_some() {
+            return Err(DialogError::InvalidLink(LinkErrorInfo {
+                missing_source,
+                missing_target,
+            }));
+        }
         Ok(Dialog {
             start_node: self.start_node,
             nodes: self.nodes,
@@ -51,12 +69,17 @@ impl Dialog {
     pub fn get_node(&self, id: &DialogNodeId) -> &DialogNode {
         self.nodes.get(id).unwrap()
     }
+
+    pub fn all_nodes(&self) -> impl Iterator<Item = &DialogNode> {
+        self.nodes.values()
+    }
 }
 
 struct DialogExecutor<'d> {
     dialog: &'d Dialog,
     current: DialogNodeId,
     path: Vec<(DialogNodeId, usize)>,
+    variables: HashMap<String, VariableValue>,
 }
 
 impl<'d> DialogExecutor<'d> {
@@ -65,6 +88,7 @@ impl<'d> DialogExecutor<'d> {
             dialog,
             current: dialog.start_node().id().clone(),
             path: Vec::new(),
+            variables: HashMap::new(),
         }
     }
 
@@ -72,6 +96,32 @@ impl<'d> DialogExecutor<'d> {
         self.dialog.get_node(&self.current)
     }
 
+    pub fn variable(&self, name: &str) -> i64 {
+        match self.variables.get(name) {
+            Some(VariableValue::Int(value)) => *value,
+            _ => 0,
+        }
+    }
+
+    pub fn string_variable(&self, name: &str) -> String {
+        match self.variables.get(name) {
+            Some(value) => value.as_display(),
+            None => String::new(),
+        }
+    }
+
+    pub fn variables(&self) -> &HashMap<String, VariableValue> {
+        &self.variables
+    }
+
+    pub fn set_variable(&mut self, name: impl Into<String>, value: i64) {
+        self.variables.insert(name.into(), VariableValue::Int(value));
+    }
+
+    pub fn set_string_variable(&mut self, name: impl Into<String>, value: impl Into<String>) {
+        self.variables.insert(name.into(), VariableValue::Text(value.into()));
+    }
+
     pub fn choices<'dd>(&'dd mut self) -> DialogExecChoices<'dd, 'd>
     where
         'd: 'dd,
@@ -86,9 +136,35 @@ impl<'d> DialogExecutor<'d> {
 
     fn choose(&mut self, index: usize) {
         self.path.push((self.current_node().id().clone(), index));
-        let chosen_link = self.current_node().links.get(index).unwrap();
+        let chosen_link = self.current_node().links.get(index).unwrap().clone();
+        for action in chosen_link.actions() {
+            self.apply_action(action);
+        }
         self.current = chosen_link.to().clone();
     }
+
+    fn apply_action(&mut self, action: &DialogAction) {
+        match action {
+            DialogAction::Set(variable, value) => {
+                let resolved = match value {
+                    ActionValue::Literal(literal) => *literal,
+                    ActionValue::Var(name) => self.variable(name),
+                };
+                self.variables
+                    .insert(variable.clone(), VariableValue::Int(resolved));
+            }
+            DialogAction::Add(variable, amount) => {
+                let updated = self.variable(variable) + amount;
+                self.variables
+                    .insert(variable.clone(), VariableValue::Int(updated));
+            }
+            DialogAction::Sub(variable, amount) => {
+                let updated = self.variable(variable) - amount;
+                self.variables
+                    .insert(variable.clone(), VariableValue::Int(updated));
+            }
+        }
+    }
 }
 
 struct DialogExecChoices<'dd, 'd> {
@@ -107,10 +183,12 @@ impl<'dd, 'd> DialogExecChoices<'dd, 'd> {
             .links
             .iter()
             .enumerate()
-            .filter(move |(index, link)| {
-                link.condition == DialogLinkCondition::None
-                    || (link.condition == DialogLinkCondition::OnlyIfNotYetChosen
-                        && !self.history.contains(index))
+            .filter(move |(index, link)| match &link.condition {
+                DialogLinkCondition::None => true,
+                DialogLinkCondition::OnlyIfNotYetChosen => !self.history.contains(index),
+                DialogLinkCondition::Expression(condition) => {
+                    condition.eval(&self.parent.variables)
+                }
             })
             .map(move |(index, link)| (index, link.text()))
     }
@@ -148,7 +226,15 @@ impl<'dd, 'd> DialogExecChoice<'dd, 'd> {
 }
 
 #[derive(PartialEq, Eq, Debug)]
-enum DialogError {}
+enum DialogError {
+    InvalidLink(LinkErrorInfo),
+}
+
+#[derive(PartialEq, Eq, Debug)]
+struct LinkErrorInfo {
+    missing_source: Option<DialogNodeId>,
+    missing_target: Option<DialogNodeId>,
+}
 
 #[derive(PartialEq, Eq, Hash, Clone, Debug)]
 struct DialogNodeId {
@@ -164,9 +250,16 @@ where
     }
 }
 
+impl std::fmt::Display for DialogNodeId {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self.id)
+    }
+}
+
 struct DialogNode {
     id: DialogNodeId,
     text: DialogText,
+    speaker: Option<String>,
     links: Vec<DialogLink>,
 }
 
@@ -175,16 +268,46 @@ impl DialogNode {
         DialogNode {
             id: id.into(),
             text: text.into(),
+            speaker: None,
+            links: Vec::new(),
+        }
+    }
+
+    pub fn new_with_speaker(
+        id: impl Into<DialogNodeId>,
+        text: impl Into<DialogText>,
+        speaker: impl Into<String>,
+    ) -> Self {
+        DialogNode {
+            id: id.into(),
+            text: text.into(),
+            speaker: Some(speaker.into()),
             links: Vec::new(),
         }
     }
 
+    pub fn new_with_links(
+        id: impl Into<DialogNodeId>,
+        text: impl Into<DialogText>,
+        links: Vec<DialogLink>,
+    ) -> Self {
+        DialogNode {
+            id: id.into(),
+            text: text.into(),
+            speaker: None,
+            links,
+        }
+    }
+
     pub fn id(&self) -> &DialogNodeId {
         &self.id
     }
     pub fn text(&self) -> &DialogText {
         &self.text
     }
+    pub fn speaker(&self) -> Option<&str> {
+        self.speaker.as_deref()
+    }
     pub fn links(&self) -> &Vec<DialogLink> {
         &self.links
     }
@@ -199,6 +322,7 @@ struct DialogLink {
     to: DialogNodeId,
     text: DialogText,
     condition: DialogLinkCondition,
+    actions: Vec<DialogAction>,
 }
 
 impl DialogLink {
@@ -213,6 +337,23 @@ impl DialogLink {
             to: to.into(),
             text: text.into(),
             condition,
+            actions: Vec::new(),
+        }
+    }
+
+    pub fn new_with_actions(
+        from: impl Into<DialogNodeId>,
+        to: impl Into<DialogNodeId>,
+        text: impl Into<DialogText>,
+        condition: DialogLinkCondition,
+        actions: Vec<DialogAction>,
+    ) -> Self {
+        DialogLink {
+            from: from.into(),
+            to: to.into(),
+            text: text.into(),
+            condition,
+            actions,
         }
     }
 
@@ -228,23 +369,134 @@ impl DialogLink {
     pub fn text(&self) -> &DialogText {
         &self.text
     }
+    pub fn actions(&self) -> &Vec<DialogAction> {
+        &self.actions
+    }
 }
 
-#[derive(PartialEq, Eq, Clone)]
+#[derive(PartialEq, Eq, Clone, Debug)]
+enum ActionValue {
+    Literal(i64),
+    Var(String),
+}
+
+#[derive(PartialEq, Eq, Clone, Debug)]
+enum DialogAction {
+    Set(String, ActionValue),
+    Add(String, i64),
+    Sub(String, i64),
+}
+
+#[derive(PartialEq, Eq, Clone, Debug)]
 enum DialogLinkCondition {
     None,
     OnlyIfNotYetChosen,
+    Expression(Condition),
+}
+
+#[derive(PartialEq, Eq, Clone, Copy, Debug)]
+enum CompareOp {
+    Eq,
+    Ne,
+    Lt,
+    Le,
+    Gt,
+    Ge,
+}
+
+impl CompareOp {
+    fn apply(&self, lhs: i64, rhs: i64) -> bool {
+        match self {
+            CompareOp::Eq => lhs == rhs,
+            CompareOp::Ne => lhs != rhs,
+            CompareOp::Lt => lhs < rhs,
+            CompareOp::Le => lhs <= rhs,
+            CompareOp::Gt => lhs > rhs,
+            CompareOp::Ge => lhs >= rhs,
+        }
+    }
+}
+
+#[derive(PartialEq, Eq, Clone, Debug)]
+enum Condition {
+    Compare(String, CompareOp, i64),
+    And(Box<Condition>, Box<Condition>),
+    Or(Box<Condition>, Box<Condition>),
+    Not(Box<Condition>),
+}
+
+impl Condition {
+    pub fn eval(&self, variables: &HashMap<String, VariableValue>) -> bool {
+        match self {
+            Condition::Compare(var, op, literal) => {
+                let lhs = match variables.get(var) {
+                    Some(VariableValue::Int(value)) => *value,
+                    _ => 0,
+                };
+                op.apply(lhs, *literal)
+            }
+            Condition::And(lhs, rhs) => lhs.eval(variables) && rhs.eval(variables),
+            Condition::Or(lhs, rhs) => lhs.eval(variables) || rhs.eval(variables),
+            Condition::Not(condition) => !condition.eval(variables),
+        }
+    }
+}
+
+#[derive(PartialEq, Eq, Clone, Debug)]
+enum VariableValue {
+    Int(i64),
+    Text(String),
+}
+
+impl VariableValue {
+    fn as_display(&self) -> String {
+        match self {
+            VariableValue::Int(value) => value.to_string(),
+            VariableValue::Text(value) => value.clone(),
+        }
+    }
+}
+
+#[derive(Clone, PartialEq, Eq, Debug)]
+enum Segment {
+    Literal(String),
+    Var(String),
 }
 
 #[derive(Clone)]
 enum DialogText {
     PlainText(String),
+    Template(Vec<Segment>),
 }
 
 impl DialogText {
-    pub fn as_plain_str(&self) -> &str {
+    pub fn as_plain_str(&self) -> std::borrow::Cow<'_, str> {
         match self {
-            Self::PlainText(plain_text) => plain_text,
+            Self::PlainText(plain_text) => std::borrow::Cow::Borrowed(plain_text.as_str()),
+            Self::Template(_) => std::borrow::Cow::Owned(self.render(&HashMap::new())),
+        }
+    }
+
+    pub fn render(&self, variables: &HashMap<String, VariableValue>) -> String {
+        match self {
+            Self::PlainText(plain_text) => plain_text.clone(),
+            Self::Template(segments) => segments
+                .iter()
+                .map(|segment| match segment {
+                    Segment::Literal(text) => text.clone(),
+                    Segment::Var(name) => variables
+                        .get(name)
+                        .map(VariableValue::as_display)
+                        .unwrap_or_default(),
+                })
+                .collect(),
+        }
+    }
+
+    pub fn segments(&self) -> Vec<Segment> {
+        match self {
+            Self::PlainText(plain_text) => vec![Segment::Literal(plain_text.clone())],
+            Self::Template(segments) => segments.clone(),
         }
     }
 }
@@ -370,4 +622,131 @@ mod tests {
         let single_choice: Vec<(usize, &DialogText)> = choices.all().collect();
         assert_eq!(single_choice.len(), 1);
     }
+
+    #[test]
+    fn expression_condition_gates_choice_on_variable_state() {
+        let dialog = DialogBuilder::new(DialogNode::new("Start", "Welcome."))
+            .add_node(DialogNode::new("Shop", "The shopkeeper eyes your purse."))
+            .add_link(DialogLink::new(
+                "Start",
+                "Shop",
+                "Buy the sword",
+                DialogLinkCondition::Expression(Condition::Compare(
+                    "gold".into(),
+                    CompareOp::Ge,
+                    10,
+                )),
+            ))
+            .build()
+            .unwrap();
+        let mut dialog_in_progress = dialog.start();
+        let choices = dialog_in_progress.choices();
+        let no_choices: Vec<(usize, &DialogText)> = choices.all().collect();
+        assert_eq!(no_choices.len(), 0);
+        dialog_in_progress.set_variable("gold", 10);
+        let choices = dialog_in_progress.choices();
+        let gated_choice: Vec<(usize, &DialogText)> = choices.all().collect();
+        assert_eq!(gated_choice.len(), 1);
+    }
+
+    #[test]
+    fn templated_text_interpolates_variables() {
+        let dialog = DialogBuilder::new(DialogNode::new(
+            "Start",
+            DialogText::Template(vec![
+                Segment::Literal("Welcome back, ".into()),
+                Segment::Var("name".into()),
+                Segment::Literal("!".into()),
+            ]),
+        ))
+        .build()
+        .unwrap();
+        let mut dialog_in_progress = dialog.start();
+        dialog_in_progress.set_variable("name", 0);
+        assert_eq!(
+            dialog_in_progress.current_node().text().render(&HashMap::new()),
+            "Welcome back, !"
+        );
+        let mut variables = HashMap::new();
+        variables.insert("name".to_string(), VariableValue::Int(42));
+        assert_eq!(
+            dialog_in_progress.current_node().text().render(&variables),
+            "Welcome back, 42!"
+        );
+    }
+
+    #[test]
+    fn templated_text_interpolates_string_variables() {
+        let dialog = DialogBuilder::new(DialogNode::new(
+            "Start",
+            DialogText::Template(vec![
+                Segment::Literal("Welcome back, ".into()),
+                Segment::Var("name".into()),
+                Segment::Literal("!".into()),
+            ]),
+        ))
+        .build()
+        .unwrap();
+        let mut dialog_in_progress = dialog.start();
+        dialog_in_progress.set_string_variable("name", "Alice");
+        assert_eq!(
+            dialog_in_progress
+                .current_node()
+                .text()
+                .render(dialog_in_progress.variables()),
+            "Welcome back, Alice!"
+        );
+        assert_eq!(dialog_in_progress.string_variable("name"), "Alice");
+    }
+
+    #[test]
+    fn node_carries_optional_speaker() {
+        let guard = DialogNode::new_with_speaker("Start", "Halt!", "Guard");
+        assert_eq!(guard.speaker(), Some("Guard"));
+        let narration = DialogNode::new("Start", "The wind howls.");
+        assert_eq!(narration.speaker(), None);
+    }
+
+    #[test]
+    fn choosing_a_link_applies_its_actions() {
+        let dialog = DialogBuilder::new(DialogNode::new("Start", "The shop awaits."))
+            .add_node(DialogNode::new("Shop", "Thanks for your coin."))
+            .add_link(DialogLink::new_with_actions(
+                "Start",
+                "Shop",
+                "Buy the sword",
+                DialogLinkCondition::None,
+                vec![
+                    DialogAction::Sub("gold".into(), 5),
+                    DialogAction::Set("met_guard".into(), ActionValue::Literal(1)),
+                ],
+            ))
+            .build()
+            .unwrap();
+        let mut dialog_in_progress = dialog.start();
+        dialog_in_progress.set_variable("gold", 10);
+        dialog_in_progress.choices().get(0).unwrap().choose();
+        assert_eq!(dialog_in_progress.variable("gold"), 5);
+        assert_eq!(dialog_in_progress.variable("met_guard"), 1);
+    }
+
+    #[test]
+    fn build_rejects_link_to_missing_node() {
+        let error = DialogBuilder::new(DialogNode::new("Start", "Hello, World!"))
+            .add_link(DialogLink::new(
+                "Start",
+                "Nowhere",
+                "Hi!",
+                DialogLinkCondition::None,
+            ))
+            .build()
+            .unwrap_err();
+        assert_eq!(
+            error,
+            DialogError::InvalidLink(LinkErrorInfo {
+                missing_source: None,
+                missing_target: Some("Nowhere".into()),
+            })
+        );
+    }
 }
@@ -1,9 +1,12 @@
+use std::collections::{HashMap, HashSet};
+
 use super::engine;
 use nom::branch::alt;
-use nom::bytes::complete::{tag, take_while};
+use nom::bytes::complete::{tag, take_while, take_while1};
 use nom::character::complete::anychar;
-use nom::multi::{many0, many_till, separated_list0};
-use nom::sequence::delimited;
+use nom::combinator::{map, opt, value};
+use nom::multi::{many0, many_till};
+use nom::sequence::{delimited, preceded};
 use nom::IResult;
 use nom::Parser;
 
@@ -11,6 +14,7 @@ use nom::Parser;
 enum DialogParseError<T> {
     DialogBuildError(engine::DialogError),
     NomError(nom::Err<T>),
+    Include(IncludeError),
 }
 
 impl<T> From<engine::DialogError> for DialogParseError<T> {
@@ -25,16 +29,28 @@ impl<T> From<nom::Err<T>> for DialogParseError<T> {
     }
 }
 
+#[derive(PartialEq, Eq, Debug)]
+enum IncludeError {
+    Resolver(String),
+    Syntax(String),
+    DuplicateNodeId(engine::DialogNodeId),
+    IncludeCycle(String),
+}
+
 #[derive(PartialEq, Eq, Hash, Clone, Copy, Debug)]
 enum Separator {
     Link,
     Node,
 }
 
+enum TopLevelItem {
+    Node(engine::DialogNode),
+    Include(String),
+}
+
 fn parse<'s>(input: &'s str) -> Result<engine::Dialog, DialogParseError<nom::error::Error<&str>>> {
     let (input, node) = parse_node(input)?;
-    let (remainder, nodes) = many0(parse_node).parse(input)?;
-    println!("{}", remainder);
+    let (_remainder, nodes) = many0(parse_node).parse(input)?;
     let mut builder = engine::DialogBuilder::new(node);
     for node in nodes {
         builder = builder.add_node(node);
@@ -42,42 +58,355 @@ fn parse<'s>(input: &'s str) -> Result<engine::Dialog, DialogParseError<nom::err
     Ok(builder.build()?)
 }
 
+fn parse_with_includes<'s>(
+    input: &'s str,
+    resolver: &impl Fn(&str) -> Result<String, String>,
+) -> Result<engine::Dialog, DialogParseError<nom::error::Error<&'s str>>> {
+    let (order, mut nodes) =
+        collect_nodes_with_includes(input, resolver).map_err(DialogParseError::Include)?;
+    let mut order = order.into_iter();
+    let first_id = order
+        .next()
+        .expect("dialog source must define at least one node");
+    let mut builder = engine::DialogBuilder::new(nodes.remove(&first_id).unwrap());
+    for id in order {
+        builder = builder.add_node(nodes.remove(&id).unwrap());
+    }
+    Ok(builder.build()?)
+}
+
+fn collect_nodes_with_includes(
+    input: &str,
+    resolver: &impl Fn(&str) -> Result<String, String>,
+) -> Result<(Vec<engine::DialogNodeId>, HashMap<engine::DialogNodeId, engine::DialogNode>), IncludeError>
+{
+    collect_nodes_with_includes_inner(input, resolver, &mut HashSet::new())
+}
+
+fn collect_nodes_with_includes_inner(
+    input: &str,
+    resolver: &impl Fn(&str) -> Result<String, String>,
+    including: &mut HashSet<String>,
+) -> Result<(Vec<engine::DialogNodeId>, HashMap<engine::DialogNodeId, engine::DialogNode>), IncludeError>
+{
+    let (_, items) = many0(parse_top_level_item)
+        .parse(input)
+        .map_err(|error| IncludeError::Syntax(format!("{error:?}")))?;
+    let mut order = Vec::new();
+    let mut nodes = HashMap::new();
+    for item in items {
+        match item {
+            TopLevelItem::Node(node) => {
+                let id = node.id().clone();
+                if nodes.contains_key(&id) {
+                    return Err(IncludeError::DuplicateNodeId(id));
+                }
+                order.push(id.clone());
+                nodes.insert(id, node);
+            }
+            TopLevelItem::Include(path) => {
+                if !including.insert(path.clone()) {
+                    return Err(IncludeError::IncludeCycle(path));
+                }
+                let source = resolver(&path).map_err(IncludeError::Resolver)?;
+                let (included_order, mut included_nodes) =
+                    collect_nodes_with_includes_inner(&source, resolver, including)?;
+                including.remove(&path);
+                for id in included_order {
+                    if nodes.contains_key(&id) {
+                        return Err(IncludeError::DuplicateNodeId(id));
+                    }
+                    let node = included_nodes.remove(&id).unwrap();
+                    order.push(id.clone());
+                    nodes.insert(id, node);
+                }
+            }
+        }
+    }
+    Ok((order, nodes))
+}
+
+fn parse_top_level_item<'s>(input: &'s str) -> IResult<&'s str, TopLevelItem> {
+    alt((
+        map(parse_include_directive, TopLevelItem::Include),
+        map(parse_node, TopLevelItem::Node),
+    ))
+    .parse(input)
+}
+
+fn parse_include_directive<'s>(input: &'s str) -> IResult<&'s str, String> {
+    let (input, _) = trim(input)?;
+    let (input, _) = tag("Include:")(input)?;
+    let (input, _) = trim(input)?;
+    let (input, path) = take_while(|ch| ch != '\n' && ch != '\r')(input)?;
+    Ok((input, path.trim().to_string()))
+}
+
 fn parse_node<'s>(input: &'s str) -> IResult<&'s str, engine::DialogNode> {
     let (input, _) = trim(input)?;
     let (input, _) = tag("Name:")(input)?;
     let (input, _) = trim(input)?;
     let (input, node_name) = identifier(input)?;
     let (input, _) = trim(input)?;
+    let (input, speaker) = opt(parse_speaker_line).parse(input)?;
     let (input, (text, separator)) =
         many_till(anychar, alt((link_separator, node_separator))).parse(input)?;
-    let text: String = text.iter().collect();
+    let mut raw_text: String = text.iter().collect();
+    if separator == Separator::Node {
+        for newline in ["\r\n", "\n"] {
+            if let Some(stripped) = raw_text.strip_suffix(newline) {
+                raw_text.truncate(stripped.len());
+                break;
+            }
+        }
+    }
+    let (speaker, raw_text) = match speaker {
+        Some(speaker) => (Some(speaker), raw_text),
+        None => extract_inline_speaker(raw_text),
+    };
+    let text = parse_dialog_text(&raw_text);
+    let mut node = match speaker {
+        Some(speaker) => engine::DialogNode::new_with_speaker(node_name, text, speaker),
+        None => engine::DialogNode::new(node_name, text),
+    };
     if separator == Separator::Link {
-        let (input, choices) =
-            separated_list0(tag("|"), take_while(|ch| ch != '|' && ch != '=')).parse(input)?;
-        let mut links = Vec::new();
-        for choice in choices {
-            links.push(parse_link(node_name, choice)?.1);
+        let choice_end = [input.find("===\r\n"), input.find("===\n")]
+            .into_iter()
+            .flatten()
+            .min()
+            .ok_or_else(|| {
+                nom::Err::Error(nom::error::Error::new(input, nom::error::ErrorKind::Tag))
+            })?;
+        let (choice_text, rest) = input.split_at(choice_end);
+        let (input, _) = node_separator(rest)?;
+        for choice in choice_text.split('|') {
+            node.add_link(parse_link(node_name, choice)?.1);
         }
-        let (input, _) = node_separator(input)?;
-        Ok((
-            input,
-            engine::DialogNode::new_with_links(node_name, text, links),
-        ))
+        Ok((input, node))
     } else {
-        Ok((input, engine::DialogNode::new(node_name, text)))
+        Ok((input, node))
     }
 }
 
+fn parse_speaker_line<'s>(input: &'s str) -> IResult<&'s str, String> {
+    let (input, _) = tag("Speaker:")(input)?;
+    let (input, _) = trim(input)?;
+    let (input, name) = identifier(input)?;
+    let (input, _) = trim(input)?;
+    Ok((input, name.to_string()))
+}
+
+fn extract_inline_speaker(text: String) -> (Option<String>, String) {
+    if let Some(idx) = text.find(" says:") {
+        let prefix = &text[..idx];
+        if !prefix.is_empty() && prefix.chars().all(is_alphanumeric) {
+            let rest = text[idx + " says:".len()..].trim().to_string();
+            return (Some(prefix.to_string()), rest);
+        }
+    }
+    (None, text)
+}
+
+fn parse_dialog_text(raw: &str) -> engine::DialogText {
+    let mut segments = Vec::new();
+    let mut literal = String::new();
+    let mut chars = raw.chars().peekable();
+    while let Some(ch) = chars.next() {
+        if ch == '[' {
+            let mut var = String::new();
+            for next in chars.by_ref() {
+                if next == ']' {
+                    break;
+                }
+                var.push(next);
+            }
+            if !literal.is_empty() {
+                segments.push(engine::Segment::Literal(std::mem::take(&mut literal)));
+            }
+            segments.push(engine::Segment::Var(var));
+        } else {
+            literal.push(ch);
+        }
+    }
+    if segments.is_empty() {
+        return engine::DialogText::from(literal);
+    }
+    if !literal.is_empty() {
+        segments.push(engine::Segment::Literal(literal));
+    }
+    engine::DialogText::Template(segments)
+}
+
+fn parse_link_condition<'s>(input: &'s str) -> IResult<&'s str, engine::DialogLinkCondition> {
+    alt((
+        map(preceded(keyword("if"), parse_condition), |condition| {
+            engine::DialogLinkCondition::Expression(condition)
+        }),
+        value(
+            engine::DialogLinkCondition::OnlyIfNotYetChosen,
+            keyword("once"),
+        ),
+    ))
+    .parse(input)
+}
+
 fn parse_link<'s>(parent: &'s str, input: &'s str) -> IResult<&'s str, engine::DialogLink> {
     let (input, _) = trim(input)?;
     let (input, target) = delimited(tag("{"), identifier, tag("}")).parse(input)?;
-    let text = input.trim();
+    let (input, condition) = opt(parse_link_condition).parse(input)?;
+    let condition = condition.unwrap_or(engine::DialogLinkCondition::None);
+    let (text, actions) = match input.find("<<") {
+        Some(idx) => {
+            let action_end = input[idx..]
+                .find(">>")
+                .map(|end| idx + end)
+                .ok_or_else(|| {
+                    nom::Err::Error(nom::error::Error::new(input, nom::error::ErrorKind::Tag))
+                })?;
+            (
+                &input[..idx],
+                parse_actions(&input[idx + "<<".len()..action_end])?,
+            )
+        }
+        None => (input, Vec::new()),
+    };
+    let text = parse_dialog_text(text.trim());
     Ok((
         "",
-        engine::DialogLink::new(parent, target, text, engine::DialogLinkCondition::None),
+        engine::DialogLink::new_with_actions(parent, target, text, condition, actions),
     ))
 }
 
+fn parse_actions<'s>(
+    input: &'s str,
+) -> Result<Vec<engine::DialogAction>, nom::Err<nom::error::Error<&'s str>>> {
+    input
+        .split(',')
+        .map(|chunk| parse_action(chunk.trim()).map(|(_, action)| action))
+        .collect()
+}
+
+fn parse_action<'s>(input: &'s str) -> IResult<&'s str, engine::DialogAction> {
+    alt((parse_set_action, parse_add_action, parse_sub_action)).parse(input)
+}
+
+fn parse_set_action<'s>(input: &'s str) -> IResult<&'s str, engine::DialogAction> {
+    let (input, _) = keyword("set")(input)?;
+    let (input, _) = trim(input)?;
+    let (input, var) = identifier(input)?;
+    let (input, _) = trim(input)?;
+    let (input, value) = parse_action_value(input)?;
+    Ok((input, engine::DialogAction::Set(var.to_string(), value)))
+}
+
+fn parse_add_action<'s>(input: &'s str) -> IResult<&'s str, engine::DialogAction> {
+    let (input, _) = keyword("add")(input)?;
+    let (input, _) = trim(input)?;
+    let (input, var) = identifier(input)?;
+    let (input, _) = trim(input)?;
+    let (input, amount) = parse_integer(input)?;
+    Ok((input, engine::DialogAction::Add(var.to_string(), amount)))
+}
+
+fn parse_sub_action<'s>(input: &'s str) -> IResult<&'s str, engine::DialogAction> {
+    let (input, _) = keyword("sub")(input)?;
+    let (input, _) = trim(input)?;
+    let (input, var) = identifier(input)?;
+    let (input, _) = trim(input)?;
+    let (input, amount) = parse_integer(input)?;
+    Ok((input, engine::DialogAction::Sub(var.to_string(), amount)))
+}
+
+fn parse_action_value<'s>(input: &'s str) -> IResult<&'s str, engine::ActionValue> {
+    alt((
+        |input| {
+            let (input, literal) = parse_integer(input)?;
+            Ok((input, engine::ActionValue::Literal(literal)))
+        },
+        |input| {
+            let (input, var) = identifier(input)?;
+            Ok((input, engine::ActionValue::Var(var.to_string())))
+        },
+    ))
+    .parse(input)
+}
+
+fn parse_integer<'s>(input: &'s str) -> IResult<&'s str, i64> {
+    let (input, negative) = opt(tag("-")).parse(input)?;
+    let (input, digits) = take_while1(|ch: char| ch.is_ascii_digit())(input)?;
+    let magnitude: i64 = digits.parse().unwrap();
+    Ok((input, if negative.is_some() { -magnitude } else { magnitude }))
+}
+
+fn keyword<'s>(word: &'static str) -> impl Fn(&'s str) -> IResult<&'s str, &'s str> {
+    move |input: &'s str| {
+        let (input, _) = trim(input)?;
+        let (rest, matched) = tag(word)(input)?;
+        match rest.chars().next() {
+            Some(ch) if is_alphanumeric(ch) => Err(nom::Err::Error(nom::error::Error::new(
+                input,
+                nom::error::ErrorKind::Tag,
+            ))),
+            _ => Ok((rest, matched)),
+        }
+    }
+}
+
+fn parse_condition<'s>(input: &'s str) -> IResult<&'s str, engine::Condition> {
+    parse_condition_or(input)
+}
+
+fn parse_condition_or<'s>(input: &'s str) -> IResult<&'s str, engine::Condition> {
+    let (input, first) = parse_condition_and(input)?;
+    let (input, rest) = many0(preceded(keyword("or"), parse_condition_and)).parse(input)?;
+    Ok((
+        input,
+        rest.into_iter()
+            .fold(first, |acc, term| engine::Condition::Or(Box::new(acc), Box::new(term))),
+    ))
+}
+
+fn parse_condition_and<'s>(input: &'s str) -> IResult<&'s str, engine::Condition> {
+    let (input, first) = parse_condition_not(input)?;
+    let (input, rest) = many0(preceded(keyword("and"), parse_condition_not)).parse(input)?;
+    Ok((
+        input,
+        rest.into_iter()
+            .fold(first, |acc, term| engine::Condition::And(Box::new(acc), Box::new(term))),
+    ))
+}
+
+fn parse_condition_not<'s>(input: &'s str) -> IResult<&'s str, engine::Condition> {
+    if let Ok((input, _)) = keyword("not")(input) {
+        let (input, condition) = parse_condition_not(input)?;
+        Ok((input, engine::Condition::Not(Box::new(condition))))
+    } else {
+        parse_comparison(input)
+    }
+}
+
+fn parse_comparison<'s>(input: &'s str) -> IResult<&'s str, engine::Condition> {
+    let (input, _) = trim(input)?;
+    let (input, var) = identifier(input)?;
+    let (input, _) = trim(input)?;
+    let (input, op) = compare_op(input)?;
+    let (input, _) = trim(input)?;
+    let (input, literal) = parse_integer(input)?;
+    Ok((input, engine::Condition::Compare(var.to_string(), op, literal)))
+}
+
+fn compare_op<'s>(input: &'s str) -> IResult<&'s str, engine::CompareOp> {
+    alt((
+        value(engine::CompareOp::Eq, tag("==")),
+        value(engine::CompareOp::Ne, tag("!=")),
+        value(engine::CompareOp::Le, tag("<=")),
+        value(engine::CompareOp::Ge, tag(">=")),
+        value(engine::CompareOp::Lt, tag("<")),
+        value(engine::CompareOp::Gt, tag(">")),
+    ))
+    .parse(input)
+}
+
 fn link_separator<'s>(input: &'s str) -> IResult<&'s str, Separator> {
     let (input, _) = alt((tag("\r\n---\r\n"), tag("\n---\n"))).parse(input)?;
     Ok((input, Separator::Link))
@@ -102,6 +431,7 @@ fn is_alphanumeric(ch: char) -> bool {
         'A'..='Z' => true,
         'a'..='z' => true,
         '0'..='9' => true,
+        '_' => true,
         _ => false,
     }
 }
@@ -170,4 +500,249 @@ Bye!
             );
         }
     }
+
+    #[test]
+    fn link_with_guard_condition() {
+        let parse_result = parse(
+            r#"Name: Start
+Hello, World!
+---
+{Shop} if gold >= 10 Buy the sword
+===
+
+Name: Shop
+The shopkeeper eyes your purse.
+===
+"#,
+        );
+        assert!(parse_result.is_ok(), "{:?}", parse_result);
+        let dialog = parse_result.unwrap();
+        let links = dialog.start_node().links();
+        assert_eq!(links.len(), 1);
+        assert_eq!(links[0].text().as_plain_str(), "Buy the sword");
+        assert_eq!(
+            links[0].condition(),
+            &engine::DialogLinkCondition::Expression(engine::Condition::Compare(
+                "gold".into(),
+                engine::CompareOp::Ge,
+                10
+            ))
+        );
+    }
+
+    #[test]
+    fn link_with_once_guard() {
+        let parse_result = parse(
+            r#"Name: Start
+Hello, World!
+---
+{Shop} once Buy the sword
+===
+
+Name: Shop
+The shopkeeper eyes your purse.
+===
+"#,
+        );
+        assert!(parse_result.is_ok(), "{:?}", parse_result);
+        let dialog = parse_result.unwrap();
+        let links = dialog.start_node().links();
+        assert_eq!(links.len(), 1);
+        assert_eq!(links[0].text().as_plain_str(), "Buy the sword");
+        assert_eq!(
+            links[0].condition(),
+            &engine::DialogLinkCondition::OnlyIfNotYetChosen
+        );
+    }
+
+    #[test]
+    fn node_text_with_variable_interpolation() {
+        let parse_result = parse(
+            r#"Name: Start
+Welcome back, [name]!
+===
+"#,
+        );
+        assert!(parse_result.is_ok(), "{:?}", parse_result);
+        let dialog = parse_result.unwrap();
+        let mut variables = std::collections::HashMap::new();
+        variables.insert("name".to_string(), engine::VariableValue::Int(1));
+        assert_eq!(
+            dialog.start_node().text().render(&variables),
+            "Welcome back, 1!"
+        );
+    }
+
+    #[test]
+    fn node_with_speaker_header() {
+        let parse_result = parse(
+            r#"Name: Start
+Speaker: Guard
+Halt! Who goes there?
+===
+"#,
+        );
+        assert!(parse_result.is_ok(), "{:?}", parse_result);
+        let dialog = parse_result.unwrap();
+        assert_eq!(dialog.start_node().speaker(), Some("Guard"));
+        assert_eq!(
+            dialog.start_node().text().as_plain_str(),
+            "Halt! Who goes there?"
+        );
+    }
+
+    #[test]
+    fn node_with_inline_speaker_prefix() {
+        let parse_result = parse(
+            r#"Name: Start
+Guard says: Halt! Who goes there?
+===
+"#,
+        );
+        assert!(parse_result.is_ok(), "{:?}", parse_result);
+        let dialog = parse_result.unwrap();
+        assert_eq!(dialog.start_node().speaker(), Some("Guard"));
+        assert_eq!(
+            dialog.start_node().text().as_plain_str(),
+            "Halt! Who goes there?"
+        );
+    }
+
+    #[test]
+    fn link_with_actions() {
+        let parse_result = parse(
+            r#"Name: Start
+Hello, World!
+---
+{Shop} Buy the sword<<sub gold 5, set met_shopkeeper 1>>
+===
+
+Name: Shop
+Welcome.
+===
+"#,
+        );
+        assert!(parse_result.is_ok(), "{:?}", parse_result);
+        let dialog = parse_result.unwrap();
+        let links = dialog.start_node().links();
+        assert_eq!(links.len(), 1);
+        assert_eq!(links[0].text().as_plain_str(), "Buy the sword");
+        assert_eq!(
+            links[0].actions(),
+            &vec![
+                engine::DialogAction::Sub("gold".into(), 5),
+                engine::DialogAction::Set(
+                    "met_shopkeeper".into(),
+                    engine::ActionValue::Literal(1)
+                ),
+            ]
+        );
+    }
+
+    #[test]
+    fn malformed_action_is_a_parse_error() {
+        let parse_result = parse(
+            r#"Name: Start
+Hello, World!
+---
+{Shop} Buy the sword<<st gold 5>>
+===
+
+Name: Shop
+Welcome.
+===
+"#,
+        );
+        assert!(parse_result.is_err());
+    }
+
+    #[test]
+    fn link_text_containing_the_word_then_is_not_mistaken_for_an_action_block() {
+        let parse_result = parse(
+            r#"Name: Home
+Hello, World!
+---
+{Home} Rest then travel
+===
+"#,
+        );
+        assert!(parse_result.is_ok(), "{:?}", parse_result);
+        let dialog = parse_result.unwrap();
+        let links = dialog.start_node().links();
+        assert_eq!(links.len(), 1);
+        assert_eq!(links[0].text().as_plain_str(), "Rest then travel");
+        assert_eq!(links[0].actions(), &Vec::new());
+    }
+
+    #[test]
+    fn include_merges_nodes_from_another_file() {
+        let main_source = r#"Name: Start
+Hello, World!
+---
+{End} Bye!
+===
+
+Include: shop.dialog
+"#;
+        let resolver = |path: &str| {
+            if path == "shop.dialog" {
+                Ok(r#"Name: End
+Goodbye!
+===
+"#
+                .to_string())
+            } else {
+                Err(format!("unknown include {path}"))
+            }
+        };
+        let dialog = parse_with_includes(main_source, &resolver).unwrap();
+        assert_eq!(dialog.start_node().id(), &"Start".into());
+        assert_eq!(dialog.get_node(&"End".into()).text().as_plain_str(), "Goodbye!");
+    }
+
+    #[test]
+    fn include_rejects_duplicate_node_ids() {
+        let main_source = r#"Name: Start
+Hello, World!
+===
+
+Include: shop.dialog
+"#;
+        let resolver = |_: &str| {
+            Ok(r#"Name: Start
+A different start.
+===
+"#
+            .to_string())
+        };
+        let error = parse_with_includes(main_source, &resolver).unwrap_err();
+        assert_eq!(
+            error,
+            DialogParseError::Include(IncludeError::DuplicateNodeId("Start".into()))
+        );
+    }
+
+    #[test]
+    fn include_rejects_cycles() {
+        let main_source = r#"Name: Start
+Hello, World!
+===
+
+Include: shop.dialog
+"#;
+        let resolver = |_: &str| {
+            Ok(r#"Name: End
+Goodbye!
+===
+
+Include: shop.dialog
+"#
+            .to_string())
+        };
+        let error = parse_with_includes(main_source, &resolver).unwrap_err();
+        assert_eq!(
+            error,
+            DialogParseError::Include(IncludeError::IncludeCycle("shop.dialog".into()))
+        );
+    }
 }
@@ -22,26 +22,106 @@ fn serialize_node(node: &engine::DialogNode) -> String {
         } else {
             link_text.push_str("\n---\n");
         }
+        let guard = match link.condition() {
+            engine::DialogLinkCondition::Expression(condition) => {
+                format!("if {} ", serialize_condition(condition))
+            }
+            engine::DialogLinkCondition::OnlyIfNotYetChosen => "once ".to_string(),
+            engine::DialogLinkCondition::None => String::new(),
+        };
+        let action_block = if link.actions().is_empty() {
+            String::new()
+        } else {
+            format!(
+                "<<{}>>",
+                link.actions()
+                    .iter()
+                    .map(serialize_action)
+                    .collect::<Vec<_>>()
+                    .join(", ")
+            )
+        };
         link_text.push_str(&format!(
-            "{{{target}}} {text}",
+            "{{{target}}} {guard}{text}{action_block}",
             target = link.to().clone().to_string(),
-            text = link.text().as_plain_str()
+            text = serialize_text(link.text())
         ));
         first = false;
     }
+    let speaker_header = match node.speaker() {
+        Some(speaker) => format!("Speaker: {speaker}\n"),
+        None => String::new(),
+    };
     output.push_str(&format!(
         r#"Name: {name}
-{text}{link_text}
+{speaker_header}{text}{link_text}
 ===
 
 "#,
         name = node.id().clone().to_string(),
-        text = node.text().as_plain_str(),
+        text = serialize_text(node.text()),
         link_text = link_text
     ));
     output
 }
 
+fn serialize_text(text: &engine::DialogText) -> String {
+    text.segments()
+        .iter()
+        .map(|segment| match segment {
+            engine::Segment::Literal(literal) => literal.clone(),
+            engine::Segment::Var(name) => format!("[{name}]"),
+        })
+        .collect()
+}
+
+fn serialize_condition(condition: &engine::Condition) -> String {
+    match condition {
+        engine::Condition::Compare(var, op, literal) => {
+            format!("{var} {op} {literal}", op = serialize_compare_op(op))
+        }
+        engine::Condition::And(lhs, rhs) => format!(
+            "{} and {}",
+            serialize_condition(lhs),
+            serialize_condition(rhs)
+        ),
+        engine::Condition::Or(lhs, rhs) => format!(
+            "{} or {}",
+            serialize_condition(lhs),
+            serialize_condition(rhs)
+        ),
+        engine::Condition::Not(condition) => format!("not {}", serialize_condition(condition)),
+    }
+}
+
+fn serialize_action(action: &engine::DialogAction) -> String {
+    match action {
+        engine::DialogAction::Set(var, value) => {
+            format!("set {var} {value}", value = serialize_action_value(value))
+        }
+        engine::DialogAction::Add(var, amount) => format!("add {var} {amount}"),
+        engine::DialogAction::Sub(var, amount) => format!("sub {var} {amount}"),
+    }
+}
+
+fn serialize_action_value(value: &engine::ActionValue) -> String {
+    match value {
+        engine::ActionValue::Literal(literal) => literal.to_string(),
+        engine::ActionValue::Var(var) => var.clone(),
+    }
+}
+
+fn serialize_compare_op(op: &engine::CompareOp) -> &'static str {
+    match op {
+        engine::CompareOp::Eq => "==",
+        engine::CompareOp::Ne => "!=",
+        engine::CompareOp::Lt => "<",
+        engine::CompareOp::Le => "<=",
+        engine::CompareOp::Gt => ">",
+        engine::CompareOp::Ge => ">=",
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -71,6 +151,147 @@ Name: End
 Bye!
 ===
 
+"#
+        );
+    }
+
+    #[test]
+    fn render_guarded_link() {
+        let mut builder = engine::DialogBuilder::new(engine::DialogNode::new_with_links(
+            "Start",
+            "Hello, World!",
+            vec![engine::DialogLink::new(
+                "Start",
+                "Shop",
+                "Buy the sword",
+                engine::DialogLinkCondition::Expression(engine::Condition::Compare(
+                    "gold".into(),
+                    engine::CompareOp::Ge,
+                    10,
+                )),
+            )],
+        ));
+        builder = builder.add_node(engine::DialogNode::new("Shop", "Welcome."));
+        let dialog = builder.build().unwrap();
+        assert_eq!(
+            serialize_dialog(&dialog),
+            r#"Name: Start
+Hello, World!
+---
+{Shop} if gold >= 10 Buy the sword
+===
+
+Name: Shop
+Welcome.
+===
+
+"#
+        );
+    }
+
+    #[test]
+    fn render_once_only_link() {
+        let mut builder = engine::DialogBuilder::new(engine::DialogNode::new_with_links(
+            "Start",
+            "Hello, World!",
+            vec![engine::DialogLink::new(
+                "Start",
+                "Shop",
+                "Buy the sword",
+                engine::DialogLinkCondition::OnlyIfNotYetChosen,
+            )],
+        ));
+        builder = builder.add_node(engine::DialogNode::new("Shop", "Welcome."));
+        let dialog = builder.build().unwrap();
+        assert_eq!(
+            serialize_dialog(&dialog),
+            r#"Name: Start
+Hello, World!
+---
+{Shop} once Buy the sword
+===
+
+Name: Shop
+Welcome.
+===
+
+"#
+        );
+    }
+
+    #[test]
+    fn render_templated_text() {
+        let builder = engine::DialogBuilder::new(engine::DialogNode::new(
+            "Start",
+            engine::DialogText::Template(vec![
+                engine::Segment::Literal("Welcome back, ".into()),
+                engine::Segment::Var("name".into()),
+                engine::Segment::Literal("!".into()),
+            ]),
+        ));
+        let dialog = builder.build().unwrap();
+        assert_eq!(
+            serialize_dialog(&dialog),
+            r#"Name: Start
+Welcome back, [name]!
+===
+
+"#
+        );
+    }
+
+    #[test]
+    fn render_node_with_speaker() {
+        let builder = engine::DialogBuilder::new(engine::DialogNode::new_with_speaker(
+            "Start",
+            "Halt! Who goes there?",
+            "Guard",
+        ));
+        let dialog = builder.build().unwrap();
+        assert_eq!(
+            serialize_dialog(&dialog),
+            r#"Name: Start
+Speaker: Guard
+Halt! Who goes there?
+===
+
+"#
+        );
+    }
+
+    #[test]
+    fn render_link_with_actions() {
+        let mut builder = engine::DialogBuilder::new(engine::DialogNode::new_with_links(
+            "Start",
+            "Hello, World!",
+            vec![engine::DialogLink::new_with_actions(
+                "Start",
+                "Shop",
+                "Buy the sword",
+                engine::DialogLinkCondition::None,
+                vec![
+                    engine::DialogAction::Sub("gold".into(), 5),
+                    engine::DialogAction::Set(
+                        "met_shopkeeper".into(),
+                        engine::ActionValue::Literal(1),
+                    ),
+                ],
+            )],
+        ));
+        builder = builder.add_node(engine::DialogNode::new("Shop", "Welcome."));
+        let dialog = builder.build().unwrap();
+        assert_eq!(
+            serialize_dialog(&dialog),
+            r#"Name: Start
+Hello, World!
+---
+{Shop} Buy the sword<<sub gold 5, set met_shopkeeper 1>>
+===
+
+Name: Shop
+Welcome.
+===
+
 "#
         );
     }